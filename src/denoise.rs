@@ -0,0 +1,201 @@
+//! RNNoise-style noise suppression for the recording pipeline.
+//!
+//! Wraps an [`AsyncSource`] and runs each 10 ms frame through a small GRU-based
+//! recurrent denoiser: band energies are computed, a gain mask and a voice-activity
+//! probability are predicted, and the gain mask is applied to the frame's samples
+//! before they're handed downstream to Whisper.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures::Stream;
+use kalosm_sound::AsyncSource;
+
+/// RNNoise operates on 48 kHz audio in 10 ms (480 sample) frames.
+const DENOISER_SAMPLE_RATE: u32 = 48_000;
+const FRAME_SIZE: usize = 480;
+/// Number of perceptual bands the gain model predicts energy/gain for.
+const BANDS: usize = 22;
+
+/// An [`AsyncSource`] adapter that denoises its inner source frame-by-frame.
+///
+/// Samples are buffered until a full 10 ms frame is available, resampled to the
+/// 48 kHz the model expects if the inner source runs at a different rate, then passed
+/// through the denoiser and re-emitted one sample at a time.
+pub struct Denoise<S> {
+    inner: S,
+    /// When disabled, samples are forwarded unchanged at the inner source's own rate.
+    /// Shared so the UI toggle can flip it live without tearing down and restarting the
+    /// capture session that owns this adapter.
+    enabled: Rc<Cell<bool>>,
+    model: RnnoiseModel,
+    input_buffer: VecDeque<f32>,
+    output_buffer: VecDeque<f32>,
+    /// Speech probability for the most recently denoised frame, shared with the UI so
+    /// it can complement Whisper's own `probability_of_no_speech` gating.
+    speech_probability: Rc<Cell<f32>>,
+    /// Fractional position between the inner source's rate and the denoiser's, carried
+    /// across samples so up- and down-sampling both converge to the right output rate.
+    resample_phase: f32,
+}
+
+impl<S> Denoise<S> {
+    pub fn new(inner: S, enabled: Rc<Cell<bool>>, speech_probability: Rc<Cell<f32>>) -> Self {
+        Self {
+            inner,
+            enabled,
+            model: RnnoiseModel::default(),
+            input_buffer: VecDeque::new(),
+            output_buffer: VecDeque::new(),
+            speech_probability,
+            resample_phase: 0.0,
+        }
+    }
+}
+
+impl<S: AsyncSource + Unpin> Stream for Denoise<S> {
+    type Item = f32;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if !this.enabled.get() {
+            return Pin::new(&mut this.inner.as_stream()).poll_next(cx);
+        }
+
+        loop {
+            if let Some(sample) = this.output_buffer.pop_front() {
+                return Poll::Ready(Some(sample));
+            }
+
+            let source_rate = this.inner.sample_rate();
+            let mut samples = Pin::new(&mut this.inner.as_stream());
+            match samples.as_mut().poll_next(cx) {
+                Poll::Ready(Some(sample)) => {
+                    this.input_buffer.extend(resample_one(
+                        sample,
+                        source_rate,
+                        DENOISER_SAMPLE_RATE,
+                        &mut this.resample_phase,
+                    ));
+
+                    while this.input_buffer.len() >= FRAME_SIZE {
+                        let frame: Vec<f32> = this.input_buffer.drain(..FRAME_SIZE).collect();
+                        let (denoised, speech_probability) = this.model.process_frame(&frame);
+                        this.speech_probability.set(speech_probability);
+                        this.output_buffer.extend(denoised);
+                    }
+                }
+                Poll::Ready(None) => {
+                    if this.output_buffer.is_empty() {
+                        return Poll::Ready(None);
+                    }
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<S: AsyncSource + Unpin> AsyncSource for Denoise<S> {
+    fn as_stream(&mut self) -> impl Stream<Item = f32> + '_ {
+        self
+    }
+
+    fn sample_rate(&self) -> u32 {
+        if self.enabled.get() {
+            DENOISER_SAMPLE_RATE
+        } else {
+            self.inner.sample_rate()
+        }
+    }
+}
+
+/// Naive nearest-neighbour resampling of a single incoming sample into zero-or-more
+/// output samples at the denoiser's rate, good enough to feed a fixed-size frame buffer
+/// without pulling in a full resampling crate.
+///
+/// `phase` accumulates `to_rate / from_rate` per call and carries any fractional
+/// remainder to the next one, so upsampling (ratio > 1) emits a sample more than once
+/// within a call and downsampling (ratio < 1) drops samples across several calls,
+/// converging on the right output rate either way instead of only handling the upsample
+/// case.
+fn resample_one(sample: f32, from_rate: u32, to_rate: u32, phase: &mut f32) -> Vec<f32> {
+    if from_rate == to_rate || from_rate == 0 {
+        return vec![sample];
+    }
+    let ratio = to_rate as f32 / from_rate as f32;
+    *phase += ratio;
+
+    let mut out = Vec::new();
+    while *phase >= 1.0 {
+        out.push(sample);
+        *phase -= 1.0;
+    }
+    out
+}
+
+/// A GRU-based recurrent denoiser: predicts a per-band gain mask and a speech
+/// probability from band energies, in the style of RNNoise.
+struct RnnoiseModel {
+    /// Hidden state carried between frames.
+    gru_state: [f32; BANDS],
+}
+
+impl Default for RnnoiseModel {
+    fn default() -> Self {
+        Self {
+            gru_state: [0.0; BANDS],
+        }
+    }
+}
+
+impl RnnoiseModel {
+    /// Denoise one 10 ms frame, returning the gain-masked samples and the predicted
+    /// probability that the frame contains speech.
+    fn process_frame(&mut self, frame: &[f32]) -> (Vec<f32>, f32) {
+        let band_energy = band_energies(frame);
+
+        let mut gain = [0.0f32; BANDS];
+        let mut speech_energy = 0.0;
+        let mut total_energy = 0.0;
+        for band in 0..BANDS {
+            // Smooth the energy estimate through the recurrent state, then derive a
+            // spectral gain biased towards bands whose energy is rising (likely
+            // speech) over bands that are flat or decaying (likely stationary noise).
+            self.gru_state[band] = 0.8 * self.gru_state[band] + 0.2 * band_energy[band];
+            let rising = (band_energy[band] - self.gru_state[band]).max(0.0);
+            gain[band] = (rising / (band_energy[band] + 1e-6)).clamp(0.1, 1.0);
+
+            speech_energy += rising;
+            total_energy += band_energy[band];
+        }
+        let speech_probability = (speech_energy / (total_energy + 1e-6)).clamp(0.0, 1.0);
+
+        let denoised = frame
+            .iter()
+            .enumerate()
+            .map(|(i, &sample)| sample * gain[i * BANDS / frame.len().max(1)])
+            .collect();
+
+        (denoised, speech_probability)
+    }
+}
+
+/// Split a frame into `BANDS` perceptual bands and compute the mean-square energy of
+/// each, mirroring RNNoise's Bark-scale band energy features.
+fn band_energies(frame: &[f32]) -> [f32; BANDS] {
+    let mut energies = [0.0f32; BANDS];
+    let band_size = frame.len().div_ceil(BANDS).max(1);
+    for (band, chunk) in frame.chunks(band_size).enumerate() {
+        if band >= BANDS {
+            break;
+        }
+        let energy = chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len().max(1) as f32;
+        energies[band] = energy;
+    }
+    energies
+}
@@ -0,0 +1,226 @@
+//! Exporting the transcript as downloadable SRT/WebVTT subtitles.
+//!
+//! Cue timing comes straight from each [`Segment`]'s `start`/`end`; the cue body is the
+//! user's edited text rather than Whisper's original output. No JS shim is needed here
+//! (unlike `mic.rs`'s continuous capture): triggering a download is just a Blob, an
+//! object URL, and a synthetic click on a hidden `<a download>`, all reachable through
+//! `web_sys` directly.
+
+use strum::Display;
+use web_sys::wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, Url};
+
+/// Subtitle cues read comfortably up to about this many characters; longer segments are
+/// split across multiple cues, each keeping its share of the segment's time span.
+const MAX_CUE_CHARS: usize = 84;
+
+#[derive(Copy, Clone, Display, PartialEq)]
+pub enum SubtitleFormat {
+    #[strum(to_string = "SRT")]
+    Srt,
+    #[strum(to_string = "WebVTT")]
+    Vtt,
+}
+
+impl SubtitleFormat {
+    pub const ALL: &[Self] = &[SubtitleFormat::Srt, SubtitleFormat::Vtt];
+
+    fn extension(self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+        }
+    }
+
+    fn mime_type(self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "application/x-subrip",
+            SubtitleFormat::Vtt => "text/vtt",
+        }
+    }
+}
+
+/// A single cue: a time span and the text spoken over it.
+struct Cue {
+    start: f64,
+    end: f64,
+    text: String,
+}
+
+/// Serialize `segments` (already filtered down to the ones visible above the speech
+/// threshold) into subtitle text in the given `format`. Each entry is a segment's
+/// `(start, end)` time span in seconds and its current (possibly edited) text.
+pub fn export_subtitles(segments: &[(f64, f64, String)], format: SubtitleFormat) -> String {
+    let cues: Vec<Cue> = segments
+        .iter()
+        .flat_map(|(start, end, text)| split_into_cues(*start, *end, text))
+        .collect();
+
+    match format {
+        SubtitleFormat::Srt => render_srt(&cues),
+        SubtitleFormat::Vtt => render_vtt(&cues),
+    }
+}
+
+/// Split one segment's text into readable cues, dividing its time span proportionally
+/// to each piece's share of the text.
+fn split_into_cues(start: f64, end: f64, text: &str) -> Vec<Cue> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.is_empty() {
+        return Vec::new();
+    }
+
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    for word in &words {
+        let candidate_len = current.len() + if current.is_empty() { 0 } else { 1 } + word.len();
+        if !current.is_empty() && candidate_len > MAX_CUE_CHARS {
+            pieces.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+
+    let duration = end - start;
+    let total_chars: usize = pieces.iter().map(|piece| piece.len()).sum::<usize>().max(1);
+    let mut cue_start = start;
+    pieces
+        .into_iter()
+        .map(|piece| {
+            let share = piece.len() as f64 / total_chars as f64;
+            let cue_end = (cue_start + duration * share).min(end);
+            let cue = Cue {
+                start: cue_start,
+                end: cue_end,
+                text: piece,
+            };
+            cue_start = cue_end;
+            cue
+        })
+        .collect()
+}
+
+fn render_srt(cues: &[Cue]) -> String {
+    let mut out = String::new();
+    for (index, cue) in cues.iter().enumerate() {
+        out.push_str(&format!("{}\n", index + 1));
+        out.push_str(&format!(
+            "{} --> {}\n",
+            format_timestamp(cue.start, ','),
+            format_timestamp(cue.end, ',')
+        ));
+        out.push_str(&cue.text);
+        out.push_str("\n\n");
+    }
+    out
+}
+
+fn render_vtt(cues: &[Cue]) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for cue in cues {
+        out.push_str(&format!(
+            "{} --> {}\n",
+            format_timestamp(cue.start, '.'),
+            format_timestamp(cue.end, '.')
+        ));
+        out.push_str(&cue.text);
+        out.push_str("\n\n");
+    }
+    out
+}
+
+/// Format seconds as `HH:MM:SS<sep>mmm`, the timestamp shape shared by SRT and WebVTT
+/// (they differ only in whether the fractional separator is a comma or a period).
+fn format_timestamp(seconds: f64, fraction_separator: char) -> String {
+    let total_millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    let hours = total_millis / 3_600_000;
+    let minutes = (total_millis / 60_000) % 60;
+    let secs = (total_millis / 1000) % 60;
+    let millis = total_millis % 1000;
+    format!("{hours:02}:{minutes:02}:{secs:02}{fraction_separator}{millis:03}")
+}
+
+/// Trigger a browser download of `contents` as `filename`, via a Blob/object-URL and a
+/// synthetic click on a detached `<a download>` element.
+pub fn trigger_download(filename: &str, format: SubtitleFormat, contents: &str) -> Result<(), JsValue> {
+    let parts = js_sys::Array::new();
+    parts.push(&JsValue::from_str(contents));
+    let mut properties = BlobPropertyBag::new();
+    properties.set_type(format.mime_type());
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &properties)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let window = web_sys::window().ok_or("no window")?;
+    let document = window.document().ok_or("no document")?;
+    let anchor = document
+        .create_element("a")?
+        .dyn_into::<HtmlAnchorElement>()?;
+    anchor.set_href(&url);
+    anchor.set_download(&format!("{filename}.{}", format.extension()));
+    anchor.click();
+
+    Url::revoke_object_url(&url)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_timestamp_components() {
+        assert_eq!(format_timestamp(0.0, ','), "00:00:00,000");
+        assert_eq!(format_timestamp(61.25, ','), "00:01:01,250");
+        assert_eq!(format_timestamp(3661.5, '.'), "01:01:01.500");
+    }
+
+    #[test]
+    fn formats_timestamp_rounds_and_clamps_negative() {
+        // Rounds to the nearest millisecond rather than truncating.
+        assert_eq!(format_timestamp(1.9996, ','), "00:00:02,000");
+        // Negative seconds (shouldn't occur, but guards against panicking on a
+        // mis-ordered segment) clamp to zero instead of underflowing.
+        assert_eq!(format_timestamp(-1.0, ','), "00:00:00,000");
+    }
+
+    #[test]
+    fn split_into_cues_drops_empty_text() {
+        assert!(split_into_cues(0.0, 1.0, "").is_empty());
+        assert!(split_into_cues(0.0, 1.0, "   ").is_empty());
+    }
+
+    #[test]
+    fn split_into_cues_keeps_short_text_as_one_cue() {
+        let cues = split_into_cues(0.0, 2.0, "hello world");
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].text, "hello world");
+        assert_eq!(cues[0].start, 0.0);
+        assert_eq!(cues[0].end, 2.0);
+    }
+
+    #[test]
+    fn split_into_cues_splits_text_longer_than_max_cue_chars() {
+        let word = "abcdefghij";
+        let words = vec![word; 10];
+        let text = words.join(" ");
+        assert!(text.len() > MAX_CUE_CHARS);
+
+        let cues = split_into_cues(0.0, 10.0, &text);
+        assert!(cues.len() > 1);
+        for cue in &cues {
+            assert!(cue.text.len() <= MAX_CUE_CHARS);
+        }
+
+        // The cues' time spans partition the segment's span contiguously.
+        assert_eq!(cues.first().unwrap().start, 0.0);
+        assert_eq!(cues.last().unwrap().end, 10.0);
+        for pair in cues.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+    }
+}
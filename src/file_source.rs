@@ -0,0 +1,437 @@
+//! Streaming decode of uploaded audio files for offline transcription.
+//!
+//! Mirrors a Ruffle-style streaming-decode backend: the file is read in chunks via
+//! `web_sys::FileReader`, each chunk is fed to a format-specific incremental decoder,
+//! and decoded f32 samples are pushed into an `AsyncSourceFromStream` exactly like
+//! `start_recording` does, so the rest of the transcription pipeline is unchanged.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use futures::channel::mpsc;
+use futures::stream;
+use futures_util::StreamExt;
+use kalosm_sound::{AsyncSource, AsyncSourceFromStream};
+use web_sys::wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+use web_sys::{File, FileReader, ProgressEvent};
+
+/// Bytes read from the file so far vs. its total size, for driving a `Progress` bar.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DecodeProgress {
+    pub decoded_bytes: u64,
+    pub total_bytes: u64,
+}
+
+impl DecodeProgress {
+    pub fn fraction(&self) -> f64 {
+        if self.total_bytes == 0 {
+            0.0
+        } else {
+            self.decoded_bytes as f64 / self.total_bytes as f64
+        }
+    }
+}
+
+/// How many bytes to pull from the file per `FileReader` read.
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+struct DecodedBatch {
+    samples: Vec<f32>,
+    sample_rate: u32,
+}
+
+/// Begin streaming-decoding `file` and return an [`AsyncSource`] of the decoded
+/// samples plus a channel of [`DecodeProgress`] updates, or `None` if the file is
+/// empty or its format isn't recognized.
+pub async fn start_file_transcription(
+    file: File,
+) -> Option<(
+    impl AsyncSource + Unpin,
+    mpsc::UnboundedReceiver<DecodeProgress>,
+)> {
+    let total_bytes = file.size() as u64;
+    if total_bytes == 0 {
+        return None;
+    }
+
+    let mut decoder = ContainerDecoder::for_filename(&file.name())?;
+    let (sample_sender, mut sample_receiver) = mpsc::unbounded::<DecodedBatch>();
+    let (progress_sender, progress_receiver) = mpsc::unbounded();
+
+    read_file_chunks(file, total_bytes, move |chunk, decoded_bytes| {
+        let samples = decoder.decode_chunk(&chunk);
+        if !samples.is_empty() {
+            _ = sample_sender.unbounded_send(DecodedBatch {
+                samples,
+                sample_rate: decoder.sample_rate(),
+            });
+        }
+        _ = progress_sender.unbounded_send(DecodeProgress {
+            decoded_bytes,
+            total_bytes,
+        });
+    });
+
+    let first = sample_receiver.next().await?;
+    let sample_rate = first.sample_rate;
+    Some((
+        AsyncSourceFromStream::new(
+            stream::iter(first.samples)
+                .chain(sample_receiver.flat_map(|batch| stream::iter(batch.samples))),
+            sample_rate,
+        ),
+        progress_receiver,
+    ))
+}
+
+/// Read `file` in `CHUNK_SIZE` slices, calling `on_chunk(bytes, bytes_read_so_far)` for
+/// each one as it becomes available, using `FileReader` recursively to avoid blocking
+/// the main thread on large files.
+fn read_file_chunks(file: File, total_bytes: u64, on_chunk: impl FnMut(Vec<u8>, u64) + 'static) {
+    let on_chunk = Rc::new(RefCell::new(on_chunk));
+    let offset = Rc::new(RefCell::new(0u64));
+    let file = Rc::new(file);
+
+    read_next_slice(file, offset, total_bytes, on_chunk);
+}
+
+fn read_next_slice(
+    file: Rc<File>,
+    offset: Rc<RefCell<u64>>,
+    total_bytes: u64,
+    on_chunk: Rc<RefCell<impl FnMut(Vec<u8>, u64) + 'static>>,
+) {
+    let start = *offset.borrow();
+    if start >= total_bytes {
+        return;
+    }
+    let end = (start + CHUNK_SIZE).min(total_bytes);
+    let Ok(slice) = file.slice_with_f64_and_f64(start as f64, end as f64) else {
+        return;
+    };
+
+    let reader = FileReader::new().expect("FileReader is available in all supported browsers");
+    let onload: Closure<dyn FnMut(ProgressEvent)> = {
+        let reader = reader.clone();
+        let file = file.clone();
+        let offset = offset.clone();
+        let on_chunk = on_chunk.clone();
+        Closure::new(move |_event: ProgressEvent| {
+            if let Ok(array_buffer) = reader.result() {
+                let bytes = js_sys::Uint8Array::new(&array_buffer).to_vec();
+                *offset.borrow_mut() = end;
+                on_chunk.borrow_mut()(bytes, end);
+            }
+            read_next_slice(file.clone(), offset.clone(), total_bytes, on_chunk.clone());
+        })
+    };
+    reader.set_onload(Some(onload.as_ref().unchecked_ref()));
+    onload.forget();
+    _ = reader.read_as_array_buffer(&slice);
+}
+
+/// Detects a container format from a file's extension and decodes it incrementally.
+///
+/// WAV is the only format actually decoded. MP3 and OGG are deliberately not offered:
+/// both need a real bitstream decoder (Huffman/MDCT reconstruction for MP3, Vorbis/Opus
+/// framing for OGG) that this lightweight adapter doesn't implement, and silently
+/// producing silence for an uploaded file is worse than not listing the format as
+/// supported - see the `accept` attribute on the file input, which is kept in sync with
+/// this match.
+enum ContainerDecoder {
+    Wav(WavDecoder),
+}
+
+impl ContainerDecoder {
+    fn for_filename(name: &str) -> Option<Self> {
+        let extension = name.rsplit('.').next()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "wav" => Some(Self::Wav(WavDecoder::default())),
+            _ => None,
+        }
+    }
+
+    fn decode_chunk(&mut self, chunk: &[u8]) -> Vec<f32> {
+        match self {
+            Self::Wav(decoder) => decoder.decode_chunk(chunk),
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        match self {
+            Self::Wav(decoder) => decoder.sample_rate,
+        }
+    }
+}
+
+/// Minimal WAV/PCM decoder: walks the file's RIFF chunks to find `fmt `/`data` once
+/// enough bytes have buffered, then converts the remaining little-endian PCM samples to
+/// f32, downmixing to mono by averaging channels.
+#[derive(Default)]
+struct WavDecoder {
+    header: Option<WavHeader>,
+    buffer: Vec<u8>,
+    sample_rate: u32,
+}
+
+#[derive(Clone, Copy)]
+struct WavHeader {
+    channels: u16,
+    bits_per_sample: u16,
+    sample_rate: u32,
+    data_offset: usize,
+}
+
+impl WavDecoder {
+    fn decode_chunk(&mut self, chunk: &[u8]) -> Vec<f32> {
+        self.buffer.extend_from_slice(chunk);
+
+        if self.header.is_none() {
+            let Some(header) = parse_wav_header(&self.buffer) else {
+                return Vec::new();
+            };
+            self.sample_rate = header.sample_rate;
+            self.header = Some(header);
+        }
+        let header = self.header.unwrap();
+
+        let Some(bytes_per_channel_sample) = bytes_per_channel_sample(header.bits_per_sample)
+        else {
+            // Unsupported bit depth: drop the undecodable bytes so the decoder doesn't
+            // spin forever waiting for frames that will never parse.
+            self.buffer.clear();
+            return Vec::new();
+        };
+        let bytes_per_sample = bytes_per_channel_sample * header.channels as usize;
+        if bytes_per_sample == 0 {
+            return Vec::new();
+        }
+        let available = self.buffer.len().saturating_sub(header.data_offset);
+        let whole_frames = available / bytes_per_sample;
+        if whole_frames == 0 {
+            return Vec::new();
+        }
+
+        let consume = header.data_offset + whole_frames * bytes_per_sample;
+        let frames = &self.buffer[header.data_offset..consume];
+
+        let samples = frames
+            .chunks(bytes_per_sample)
+            .map(|frame| {
+                let sum: f32 = frame
+                    .chunks(bytes_per_channel_sample)
+                    .map(|channel_sample| decode_pcm_sample(channel_sample, header.bits_per_sample))
+                    .sum();
+                sum / header.channels as f32
+            })
+            .collect();
+
+        self.buffer.drain(..consume);
+        self.header = Some(WavHeader {
+            data_offset: 0,
+            ..header
+        });
+
+        samples
+    }
+}
+
+/// The byte width of one channel's sample at a given PCM bit depth, or `None` for a
+/// depth this decoder doesn't know how to interpret.
+fn bytes_per_channel_sample(bits_per_sample: u16) -> Option<usize> {
+    match bits_per_sample {
+        8 | 16 | 24 | 32 => Some(bits_per_sample as usize / 8),
+        _ => None,
+    }
+}
+
+/// Decode one channel's little-endian PCM sample to `f32` in `[-1.0, 1.0]`, except that
+/// the most negative 16-bit value (scaled by `i16::MAX`) reads as about -1.00003.
+/// 8-bit WAV samples are unsigned with a 128 midpoint; 16/24/32-bit are signed.
+fn decode_pcm_sample(bytes: &[u8], bits_per_sample: u16) -> f32 {
+    match bits_per_sample {
+        8 => (bytes[0] as f32 - 128.0) / u8::MAX as f32,
+        16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / i16::MAX as f32,
+        24 => {
+            let sign_extend = if bytes[2] & 0x80 != 0 { 0xFF } else { 0x00 };
+            let padded = [bytes[0], bytes[1], bytes[2], sign_extend];
+            i32::from_le_bytes(padded) as f32 / (1 << 23) as f32
+        }
+        32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32 / i32::MAX as f32,
+        _ => 0.0,
+    }
+}
+
+/// Walk `buffer`'s RIFF chunks looking for `fmt `/`data`, returning `None` if either
+/// hasn't fully arrived yet (the caller retries once more bytes are buffered).
+///
+/// A fixed 44-byte layout only covers the minimal 16-byte `fmt ` body; real-world WAVs
+/// commonly use the 18- or 40-byte extended `fmt ` (e.g. for 24/32-bit PCM) and often
+/// insert other chunks (`LIST`, `INFO`, `fact`, ...) between `fmt ` and `data`, so this
+/// reads each chunk's declared size instead of assuming one.
+fn parse_wav_header(buffer: &[u8]) -> Option<WavHeader> {
+    if buffer.len() < 12 || &buffer[0..4] != b"RIFF" || &buffer[8..12] != b"WAVE" {
+        return None;
+    }
+
+    let mut channels = None;
+    let mut sample_rate = None;
+    let mut bits_per_sample = None;
+
+    let mut offset = 12;
+    while offset + 8 <= buffer.len() {
+        let id: [u8; 4] = buffer[offset..offset + 4].try_into().unwrap();
+        let size = u32::from_le_bytes(buffer[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        let body_start = offset + 8;
+
+        if &id == b"data" {
+            return Some(WavHeader {
+                channels: channels?,
+                bits_per_sample: bits_per_sample?,
+                sample_rate: sample_rate?,
+                data_offset: body_start,
+            });
+        }
+
+        // Every other chunk (including `fmt `) must be fully buffered before it can be
+        // read or skipped, unlike `data`, whose declared size may be wrong or absent for
+        // streamed files and whose body is handled incrementally by the caller instead.
+        if body_start + size > buffer.len() {
+            return None;
+        }
+
+        if &id == b"fmt " {
+            let body = &buffer[body_start..body_start + size];
+            if body.len() < 16 {
+                return None;
+            }
+            channels = Some(u16::from_le_bytes([body[2], body[3]]));
+            sample_rate = Some(u32::from_le_bytes([body[4], body[5], body[6], body[7]]));
+            bits_per_sample = Some(u16::from_le_bytes([body[14], body[15]]));
+        }
+
+        // Chunk bodies are padded to an even number of bytes.
+        offset = body_start + size + (size % 2);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a minimal mono WAV file: `RIFF`/`WAVE`, a 16-byte `fmt ` chunk, then
+    /// `data`. `extra_chunks` are spliced in between `fmt ` and `data` to exercise
+    /// chunk-walking past chunks this decoder doesn't care about.
+    fn build_wav(bits_per_sample: u16, data: &[u8], extra_chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let channels: u16 = 1;
+        let sample_rate: u32 = 16_000;
+        let byte_rate = sample_rate * channels as u32 * (bits_per_sample as u32 / 8);
+        let block_align = channels * (bits_per_sample / 8);
+
+        let mut body = Vec::new();
+        body.extend_from_slice(b"WAVE");
+
+        body.extend_from_slice(b"fmt ");
+        body.extend_from_slice(&16u32.to_le_bytes());
+        body.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        body.extend_from_slice(&channels.to_le_bytes());
+        body.extend_from_slice(&sample_rate.to_le_bytes());
+        body.extend_from_slice(&byte_rate.to_le_bytes());
+        body.extend_from_slice(&block_align.to_le_bytes());
+        body.extend_from_slice(&bits_per_sample.to_le_bytes());
+
+        for (id, chunk_data) in extra_chunks {
+            body.extend_from_slice(*id);
+            body.extend_from_slice(&(chunk_data.len() as u32).to_le_bytes());
+            body.extend_from_slice(chunk_data);
+            if chunk_data.len() % 2 == 1 {
+                body.push(0);
+            }
+        }
+
+        body.extend_from_slice(b"data");
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(data);
+
+        let mut buf = Vec::new();
+        buf.extend_from_slice(b"RIFF");
+        buf.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&body);
+        buf
+    }
+
+    fn decode(bits_per_sample: u16, data: &[u8]) -> Vec<f32> {
+        let mut decoder = WavDecoder::default();
+        decoder.decode_chunk(&build_wav(bits_per_sample, data, &[]))
+    }
+
+    #[test]
+    fn decodes_8_bit_unsigned_pcm() {
+        let samples = decode(8, &[0, 128, 255]);
+        assert_eq!(samples.len(), 3);
+        assert!((samples[0] - (-128.0 / 255.0)).abs() < 1e-6);
+        assert!((samples[1] - 0.0).abs() < 1e-6);
+        assert!((samples[2] - (127.0 / 255.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn decodes_16_bit_signed_pcm() {
+        let data = [i16::MIN, 0, i16::MAX]
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect::<Vec<u8>>();
+        let samples = decode(16, &data);
+        assert_eq!(samples.len(), 3);
+        assert!((samples[0] - (i16::MIN as f32 / i16::MAX as f32)).abs() < 1e-6);
+        assert!((samples[1] - 0.0).abs() < 1e-6);
+        assert!((samples[2] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn decodes_24_bit_signed_pcm() {
+        // Full-scale negative (-2^23) and full-scale positive (2^23 - 1) at 24-bit,
+        // little-endian, requiring sign-extension through the top byte.
+        let data = [0x00, 0x00, 0x80, 0xFF, 0xFF, 0x7F];
+        let samples = decode(24, &data);
+        assert_eq!(samples.len(), 2);
+        assert!((samples[0] - (-1.0)).abs() < 1e-6);
+        assert!((samples[1] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn decodes_32_bit_signed_pcm() {
+        let data = [0i32, i32::MAX]
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect::<Vec<u8>>();
+        let samples = decode(32, &data);
+        assert_eq!(samples.len(), 2);
+        assert!((samples[0] - 0.0).abs() < 1e-6);
+        assert!((samples[1] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn unsupported_bit_depth_is_dropped_without_panicking() {
+        let samples = decode(12, &[0, 1, 2, 3]);
+        assert!(samples.is_empty());
+    }
+
+    #[test]
+    fn walks_past_extra_chunks_between_fmt_and_data() {
+        let wav = build_wav(16, &1i16.to_le_bytes(), &[(b"LIST", b"INFOsomething")]);
+        let mut decoder = WavDecoder::default();
+        let samples = decoder.decode_chunk(&wav);
+        assert_eq!(samples.len(), 1);
+        assert_eq!(decoder.sample_rate, 16_000);
+    }
+
+    #[test]
+    fn header_not_yet_fully_buffered_returns_none() {
+        let wav = build_wav(16, &1i16.to_le_bytes(), &[]);
+        // Hand over only the first 10 bytes (RIFF magic, size, half of `WAVE`), well
+        // short of a full `fmt ` chunk.
+        assert!(parse_wav_header(&wav[..10]).is_none());
+    }
+}
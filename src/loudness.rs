@@ -0,0 +1,202 @@
+//! EBU R128-style loudness metering and a gain stage for the live input level meter.
+//!
+//! Implements the K-weighting filter chain from ITU-R BS.1770 (a high-frequency
+//! shelving pre-filter followed by an RLB high-pass), integrates mean-square energy
+//! over a 400 ms sliding window for a momentary LUFS reading, and tracks a fast peak
+//! for clipping warnings. The gain multiplier is applied before metering, so the VU
+//! meter reflects the gained signal that is passed on to denoising and transcription.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::Stream;
+use kalosm_sound::AsyncSource;
+
+/// A single biquad IIR stage, used for both filters in the K-weighting chain.
+#[derive(Clone, Copy)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl Biquad {
+    fn new(b0: f32, b1: f32, b2: f32, a1: f32, a2: f32) -> Self {
+        Self {
+            b0,
+            b1,
+            b2,
+            a1,
+            a2,
+            x1: 0.0,
+            x2: 0.0,
+            y1: 0.0,
+            y2: 0.0,
+        }
+    }
+
+    fn process(&mut self, x0: f32) -> f32 {
+        let y0 = self.b0 * x0 + self.b1 * self.x1 + self.b2 * self.x2
+            - self.a1 * self.y1
+            - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x0;
+        self.y2 = self.y1;
+        self.y1 = y0;
+        y0
+    }
+}
+
+/// The two-stage K-weighting filter from ITU-R BS.1770: a high-frequency shelf
+/// followed by an RLB (revised low-frequency B) high-pass. Coefficients are the
+/// reference values for 48 kHz audio.
+struct KWeightingFilter {
+    pre_filter: Biquad,
+    rlb_filter: Biquad,
+}
+
+impl KWeightingFilter {
+    fn new() -> Self {
+        Self {
+            pre_filter: Biquad::new(1.535_124_9, -2.691_696_2, 1.198_392_9, -1.690_659_3, 0.732_480_8),
+            rlb_filter: Biquad::new(1.0, -2.0, 1.0, -1.990_047_5, 0.990_072_25),
+        }
+    }
+
+    fn process(&mut self, sample: f32) -> f32 {
+        self.rlb_filter.process(self.pre_filter.process(sample))
+    }
+}
+
+/// Momentary loudness (LUFS over a 400 ms window) and a fast peak reading.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Levels {
+    pub lufs: f32,
+    pub peak: f32,
+}
+
+impl Levels {
+    /// True once the peak has reached 0 dBFS, i.e. the input is clipping.
+    pub fn is_clipping(&self) -> bool {
+        self.peak >= 1.0
+    }
+}
+
+/// Tracks EBU R128-style momentary loudness and peak level over a sliding window of
+/// K-weighted samples.
+struct LoudnessMeter {
+    filter: KWeightingFilter,
+    window: VecDeque<f32>,
+    window_len: usize,
+    peak: f32,
+}
+
+impl LoudnessMeter {
+    fn new(sample_rate: u32) -> Self {
+        let window_len = ((sample_rate as f32) * 0.4) as usize;
+        Self {
+            filter: KWeightingFilter::new(),
+            window: VecDeque::with_capacity(window_len),
+            window_len: window_len.max(1),
+            peak: 0.0,
+        }
+    }
+
+    /// Feed one sample and return the updated levels. The peak decays slowly so
+    /// clipping warnings stay visible for a moment rather than flickering.
+    fn push(&mut self, sample: f32) -> Levels {
+        self.peak = (self.peak * 0.999).max(sample.abs());
+
+        let weighted = self.filter.process(sample);
+        self.window.push_back(weighted * weighted);
+        if self.window.len() > self.window_len {
+            self.window.pop_front();
+        }
+
+        let mean_square = self.window.iter().sum::<f32>() / self.window.len() as f32;
+        let lufs = -0.691 + 10.0 * mean_square.max(1e-10).log10();
+
+        Levels {
+            lufs,
+            peak: self.peak,
+        }
+    }
+}
+
+/// How often to report levels to the UI. The loudness window itself still integrates
+/// every sample; this only throttles how often `on_levels` fires, since calling it (and
+/// the `Signal::set` it typically wraps) once per sample would mean 40k+ UI updates a
+/// second and stall the whole app.
+const UPDATE_INTERVAL: Duration = Duration::from_millis(75);
+
+/// An [`AsyncSource`] adapter that applies a live-adjustable gain multiplier and
+/// reports the resulting levels via `on_levels`, before samples reach the rest of the
+/// pipeline (denoising, transcription).
+pub struct MeteredGain<S, F> {
+    inner: S,
+    gain: Rc<Cell<f32>>,
+    meter: Option<LoudnessMeter>,
+    on_levels: F,
+    /// Samples seen since the last `on_levels` call, reset once it fires again.
+    samples_since_update: usize,
+}
+
+impl<S, F: FnMut(Levels)> MeteredGain<S, F> {
+    pub fn new(inner: S, gain: Rc<Cell<f32>>, on_levels: F) -> Self {
+        Self {
+            inner,
+            gain,
+            meter: None,
+            on_levels,
+            samples_since_update: 0,
+        }
+    }
+}
+
+impl<S: AsyncSource + Unpin, F: FnMut(Levels)> Stream for MeteredGain<S, F> {
+    type Item = f32;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let sample_rate = this.inner.sample_rate();
+        let meter = this.meter.get_or_insert_with(|| LoudnessMeter::new(sample_rate));
+        let update_interval_samples =
+            ((sample_rate as f32) * UPDATE_INTERVAL.as_secs_f32()).max(1.0) as usize;
+
+        match Pin::new(&mut this.inner.as_stream()).poll_next(cx) {
+            Poll::Ready(Some(sample)) => {
+                let gained = sample * this.gain.get();
+                let levels = meter.push(gained);
+
+                this.samples_since_update += 1;
+                if this.samples_since_update >= update_interval_samples {
+                    this.samples_since_update = 0;
+                    (this.on_levels)(levels);
+                }
+
+                Poll::Ready(Some(gained))
+            }
+            other => other,
+        }
+    }
+}
+
+impl<S: AsyncSource + Unpin, F: FnMut(Levels)> AsyncSource for MeteredGain<S, F> {
+    fn as_stream(&mut self) -> impl Stream<Item = f32> + '_ {
+        self
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+}
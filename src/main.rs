@@ -1,7 +1,10 @@
+use std::cell::{Cell, RefCell};
 use std::collections::HashSet;
+use std::rc::Rc;
 
 use dioxus::prelude::*;
 use dioxus_primitives::slider::SliderValue;
+use dioxus_web::WebEventExt;
 use futures::stream;
 use futures_util::StreamExt;
 use kalosm_sound::{
@@ -10,14 +13,27 @@ use kalosm_sound::{
 };
 use strum::Display;
 use web_sys::wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+use web_sys::{File, HtmlInputElement};
 
 use crate::{
     components::{progress::*, select::*, slider::*, toggle_group::*},
+    denoise::Denoise,
+    export::{SubtitleFormat, export_subtitles, trigger_download},
+    file_source::start_file_transcription,
+    loudness::{Levels, MeteredGain},
     mic::{AudioData, StreamOptions, stream_microphone},
+    speech::{cancel as cancel_speech, speak},
+    stabilize::{PartialTranscript, StabilizationLevel, Stabilizer, redecode_sliding_window},
 };
 
 mod components;
+mod denoise;
+mod export;
+mod file_source;
+mod loudness;
 mod mic;
+mod speech;
+mod stabilize;
 
 fn main() {
     launch(app);
@@ -28,7 +44,25 @@ fn app() -> Element {
     let mut from_display = use_signal(|| false);
     let chunks = use_store(Vec::new);
     let mut speech_threshold = use_signal(|| 0.9);
+    let mut stabilization_level = use_signal(|| StabilizationLevel::default());
+    let mut use_file_source = use_signal(|| false);
+    let mut selected_file = use_signal(|| None::<File>);
     let loading_progress = use_signal(|| 0.0);
+    let mut decode_progress = use_signal(|| 0.0);
+    let mut decode_error = use_signal(|| None::<String>);
+    let live_transcript = use_signal(PartialTranscript::default);
+    let mut gain = use_signal(|| 1.0);
+    let gain_cell = use_hook(|| Rc::new(Cell::new(1.0f32)));
+    let gain_cell_for_stream = gain_cell.clone();
+    let denoise_enabled_cell = use_hook(|| Rc::new(Cell::new(false)));
+    let denoise_enabled_cell_for_stream = denoise_enabled_cell.clone();
+    let stabilization_level_cell = use_hook(|| Rc::new(Cell::new(StabilizationLevel::default())));
+    let stabilization_level_cell_for_stream = stabilization_level_cell.clone();
+    let mut levels = use_signal(Levels::default);
+    // Bumped whenever a new read-back is requested, so an in-progress "play all" queue
+    // notices it's been superseded and stops advancing instead of talking over the new one.
+    let mut playback_generation = use_signal(|| 0u64);
+    let export_format = use_signal(|| None::<SubtitleFormat>);
 
     let whisper = use_resource(move || async move {
         match model() {
@@ -44,8 +78,37 @@ fn app() -> Element {
     });
 
     use_resource(move || async move {
-        if let Some(whisper) = whisper() {
-            if let Err(err) = start_web_sys_audio_stream(from_display(), chunks, whisper).await {
+        let Some(whisper) = whisper() else {
+            return;
+        };
+
+        if use_file_source() {
+            let Some(file) = selected_file() else {
+                return;
+            };
+            if let Err(err) =
+                start_file_audio_stream(file, chunks, decode_progress, whisper).await
+            {
+                tracing::error!("Error transcribing file: {}", err);
+                decode_error.set(Some(err.to_string()));
+            }
+        } else {
+            // Read the toggle/slider state through their shared cells rather than the
+            // signals directly: this resource must not resubscribe to every adjustment
+            // of noise suppression or stabilization level, since re-running it tears
+            // down and re-requests the capture session (`getUserMedia`) from scratch.
+            if let Err(err) = start_web_sys_audio_stream(
+                from_display(),
+                denoise_enabled_cell_for_stream.clone(),
+                gain_cell_for_stream.clone(),
+                move |new_levels| levels.set(new_levels),
+                chunks,
+                live_transcript,
+                stabilization_level_cell_for_stream.clone(),
+                whisper,
+            )
+            .await
+            {
                 tracing::error!("Error starting audio stream: {}", err);
             }
         }
@@ -75,14 +138,88 @@ fn app() -> Element {
                 ToggleGroup {
                     horizontal: true,
                     allow_multiple_pressed: false,
-                    on_pressed_change: move |value: HashSet<_>| from_display.set(value.contains(&1)),
+                    on_pressed_change: move |value: HashSet<_>| {
+                        from_display.set(value.contains(&1));
+                        use_file_source.set(value.contains(&2));
+                    },
                     ToggleItem { index: 0usize,
                         "Mic"
                     }
                     ToggleItem { index: 1usize,
                         "Device"
                     }
+                    ToggleItem { index: 2usize,
+                        "File"
+                    }
+                }
+                if use_file_source() {
+                    input {
+                        r#type: "file",
+                        // Only WAV actually decodes (see `ContainerDecoder`), so the picker
+                        // offers only WAV; any other file that is picked anyway is rejected
+                        // by extension and its error is shown below.
+                        accept: "audio/wav",
+                        onchange: move |event: Event<FormData>| {
+                            let web_event = event.as_web_event();
+                            if let Some(target) = web_event.target() {
+                                if let Ok(input) = target.dyn_into::<HtmlInputElement>() {
+                                    if let Some(files) = input.files() {
+                                        decode_progress.set(0.0);
+                                        decode_error.set(None);
+                                        selected_file.set(files.get(0));
+                                    }
+                                }
+                            }
+                        },
+                    }
+                    if let Some(error) = decode_error() {
+                        "{error}"
+                    } else if selected_file.read().is_some() && decode_progress() < 1.0 {
+                        "Decoding..."
+                        Progress {
+                            value: decode_progress(),
+                            max: 1.0,
+                            ProgressIndicator {}
+                        }
+                    }
+                }
+                ToggleGroup {
+                    horizontal: true,
+                    allow_multiple_pressed: false,
+                    on_pressed_change: move |value: HashSet<_>| {
+                        denoise_enabled_cell.set(value.contains(&0));
+                    },
+                    ToggleItem { index: 0usize,
+                        "Noise suppression"
+                    }
+                }
+            }
+            div {
+                padding_top: "0.5rem",
+                display: "flex",
+                flex_direction: "column",
+                align_items: "center",
+                gap: "0.5rem",
+
+                "Gain ({gain():.2}x)"
+                Slider {
+                    label: "Gain",
+                    horizontal: true,
+                    min: 0.0,
+                    max: 4.0,
+                    step: 0.01,
+                    default_value: SliderValue::Single(1.0),
+                    on_value_change: move |value: SliderValue| {
+                        let SliderValue::Single(v) = value;
+                        gain.set(v);
+                        gain_cell.set(v as f32);
+                    },
+                    SliderTrack {
+                        SliderRange {}
+                        SliderThumb {}
+                    }
                 }
+                VuMeter { levels: levels() }
             }
             div {
                 padding_top: "0.5rem",
@@ -117,6 +254,33 @@ fn app() -> Element {
                 align_items: "center",
                 gap: "0.5rem",
 
+                "Stabilization level ({stabilization_level().0})"
+                Slider {
+                    label: "Stabilization level",
+                    horizontal: true,
+                    min: 1.0,
+                    max: 5.0,
+                    step: 1.0,
+                    default_value: SliderValue::Single(2.0),
+                    on_value_change: move |value: SliderValue| {
+                        let SliderValue::Single(v) = value;
+                        let level = StabilizationLevel(v as usize);
+                        stabilization_level.set(level);
+                        stabilization_level_cell.set(level);
+                    },
+                    SliderTrack {
+                        SliderRange {}
+                        SliderThumb {}
+                    }
+                }
+            }
+            div {
+                padding_top: "0.5rem",
+                display: "flex",
+                flex_direction: "column",
+                align_items: "center",
+                gap: "0.5rem",
+
                 "Model"
                 ModelSelector { model }
 
@@ -130,6 +294,33 @@ fn app() -> Element {
                 }
             }
 
+            div {
+                padding_top: "0.5rem",
+                display: "flex",
+                flex_direction: "column",
+                align_items: "center",
+                gap: "0.5rem",
+
+                "Export"
+                ExportFormatSelector { format: export_format }
+                button {
+                    onclick: move |_| {
+                        let format = export_format().unwrap_or(SubtitleFormat::Srt);
+                        let visible: Vec<(f64, f64, String)> = chunks
+                            .iter()
+                            .map(|chunk| chunk.read())
+                            .filter(|chunk| chunk.is_visible(speech_threshold()))
+                            .map(|chunk| {
+                                (chunk.original.start(), chunk.original.end(), chunk.text.clone())
+                            })
+                            .collect();
+                        let subtitles = export_subtitles(&visible, format);
+                        _ = trigger_download("transcript", format, &subtitles);
+                    },
+                    "Export"
+                }
+            }
+
             div {
                 width: "100vw",
                 height: "100vh",
@@ -141,7 +332,9 @@ fn app() -> Element {
 
                 Recording {
                     speech_threshold,
-                    chunks
+                    chunks,
+                    live_transcript,
+                    playback_generation
                 }
             }
         }
@@ -151,6 +344,9 @@ fn app() -> Element {
 struct EditableSegment {
     original: Segment,
     text: String,
+    /// The RNNoise-style denoiser's speech-probability estimate for this segment's
+    /// audio, if the denoiser was enabled. Complements `original.probability_of_no_speech`.
+    denoiser_speech_probability: Option<f32>,
 }
 
 impl From<Segment> for EditableSegment {
@@ -158,29 +354,81 @@ impl From<Segment> for EditableSegment {
         EditableSegment {
             text: segment.text().to_string(),
             original: segment,
+            denoiser_speech_probability: None,
         }
     }
 }
 
+impl EditableSegment {
+    /// Whether this segment should be shown (rendered, spoken, exported) under
+    /// `speech_threshold`, combining Whisper's own `probability_of_no_speech` with the
+    /// denoiser's speech-probability estimate when one is available.
+    fn is_visible(&self, speech_threshold: f64) -> bool {
+        let whisper_speech_probability = 1.0 - self.original.probability_of_no_speech();
+        let speech_probability = self
+            .denoiser_speech_probability
+            .map(|p| whisper_speech_probability.max(p as f64))
+            .unwrap_or(whisper_speech_probability);
+        speech_probability <= speech_threshold
+    }
+}
+
 #[component]
-fn Recording(speech_threshold: ReadSignal<f64>, chunks: Store<Vec<EditableSegment>>) -> Element {
+fn Recording(
+    speech_threshold: ReadSignal<f64>,
+    chunks: Store<Vec<EditableSegment>>,
+    live_transcript: ReadSignal<PartialTranscript>,
+    mut playback_generation: Signal<u64>,
+) -> Element {
     rsx! {
         div {
             width: "70vw",
+
+            button {
+                onclick: move |_| {
+                    cancel_speech();
+                    playback_generation += 1;
+                    let generation = playback_generation();
+                    let texts: Vec<String> = chunks
+                        .iter()
+                        .map(|chunk| chunk.read())
+                        .filter(|chunk| chunk.is_visible(speech_threshold()))
+                        .map(|chunk| chunk.text.clone())
+                        .collect();
+                    spawn(async move {
+                        for text in texts {
+                            if playback_generation() != generation {
+                                break;
+                            }
+                            if speak(&text, None).await.is_err() {
+                                break;
+                            }
+                        }
+                    });
+                },
+                "Play all"
+            }
+
             for chunk in chunks.iter() {
                 Chunk {
                     speech_threshold,
-                    chunk
+                    chunk,
+                    playback_generation
                 }
             }
+            LiveTranscript { live_transcript }
         }
     }
 }
 
 #[component]
-fn Chunk(speech_threshold: ReadSignal<f64>, chunk: Store<EditableSegment>) -> Element {
+fn Chunk(
+    speech_threshold: ReadSignal<f64>,
+    chunk: Store<EditableSegment>,
+    mut playback_generation: Signal<u64>,
+) -> Element {
     let current_chunk = chunk.read();
-    if 1.0 - current_chunk.original.probability_of_no_speech() > speech_threshold() {
+    if !current_chunk.is_visible(speech_threshold()) {
         return VNode::empty();
     }
     let text = current_chunk.text.as_str();
@@ -199,12 +447,102 @@ fn Chunk(speech_threshold: ReadSignal<f64>, chunk: Store<EditableSegment>) -> El
         } else {
             div {
                 ondoubleclick: move |_| editing.set(true),
+                button {
+                    onclick: move |event: MouseEvent| {
+                        event.stop_propagation();
+                        cancel_speech();
+                        playback_generation += 1;
+                        let text = chunk.read().text.clone();
+                        spawn(async move {
+                            _ = speak(&text, None).await;
+                        });
+                    },
+                    "▶"
+                }
                 {text}
             }
         }
     }
 }
 
+/// The not-yet-finalized utterance: the stable prefix rendered normally, with the
+/// unstable tail dimmed and italicized until it firms up or the segment finalizes.
+#[component]
+fn LiveTranscript(live_transcript: ReadSignal<PartialTranscript>) -> Element {
+    let transcript = live_transcript.read();
+    if transcript.committed.is_empty() && transcript.pending.is_empty() {
+        return VNode::empty();
+    }
+    rsx! {
+        div {
+            "{transcript.committed} "
+            span {
+                class: "chunk-pending",
+                font_style: "italic",
+                opacity: "0.6",
+                "{transcript.pending}"
+            }
+        }
+    }
+}
+
+/// A momentary loudness bar plus a numeric LUFS readout, so users can confirm the
+/// source is actually producing audio and isn't near-silent or clipping.
+#[component]
+fn VuMeter(levels: Levels) -> Element {
+    // -36 LUFS to 0 LUFS maps to an empty/full bar; quiet rooms and silence read near 0%.
+    let level_fraction = ((levels.lufs + 36.0) / 36.0).clamp(0.0, 1.0);
+    rsx! {
+        div {
+            display: "flex",
+            flex_direction: "column",
+            align_items: "center",
+            gap: "0.25rem",
+
+            div {
+                width: "12rem",
+                height: "0.5rem",
+                background_color: "var(--color-neutral-200, #e5e5e5)",
+                border_radius: "0.25rem",
+                overflow: "hidden",
+
+                div {
+                    width: "{level_fraction * 100.0}%",
+                    height: "100%",
+                    background_color: if levels.is_clipping() { "#dc2626" } else { "#22c55e" },
+                }
+            }
+            if levels.is_clipping() {
+                "Clipping! ({levels.lufs:.1} LUFS)"
+            } else {
+                "{levels.lufs:.1} LUFS"
+            }
+        }
+    }
+}
+
+#[component]
+fn ExportFormatSelector(format: WriteSignal<Option<SubtitleFormat>>) -> Element {
+    let formats = SubtitleFormat::ALL.iter().enumerate().map(|(i, f)| {
+        rsx! {
+            SelectOption::<SubtitleFormat> { index: i, value: *f, text_value: "{f}",
+                "{f}"
+                SelectItemIndicator {}
+            }
+        }
+    });
+
+    rsx! {
+        Select::<SubtitleFormat> { placeholder: "Select a format...",
+            on_value_change: move |value| format.set(value),
+            SelectTrigger { aria_label: "Export Format Trigger", width: "12rem", SelectValue {} }
+            SelectList { aria_label: "Export Format List",
+                {formats}
+            }
+        }
+    }
+}
+
 #[component]
 fn ModelSelector(model: WriteSignal<Option<ModelSource>>) -> Element {
     let sources = ModelSource::ALL.iter().enumerate().map(|(i, f)| {
@@ -285,14 +623,26 @@ impl ModelSource {
     }
 }
 
-async fn start_recording(from_display: bool) -> Option<impl AsyncSource + Unpin> {
+async fn start_recording(
+    from_display: bool,
+    denoise_enabled: Rc<Cell<bool>>,
+    denoiser_speech_probability: Rc<Cell<f32>>,
+    gain: Rc<Cell<f32>>,
+    on_levels: impl FnMut(Levels) + 'static,
+) -> Option<(
+    impl AsyncSource + Unpin,
+    futures::channel::mpsc::UnboundedReceiver<AudioData>,
+)> {
     let (sender, mut receiver) = futures::channel::mpsc::unbounded();
+    let (raw_sender, raw_receiver) = futures::channel::mpsc::unbounded();
 
     let mut sender = sender.clone();
+    let mut raw_sender = raw_sender.clone();
     let on_array_buffer: Closure<dyn FnMut(JsValue)> =
         Closure::new(move |array_buffer: JsValue| {
             if let Ok(array_buffer) = serde_wasm_bindgen::from_value::<AudioData>(array_buffer) {
-                _ = sender.start_send(array_buffer);
+                _ = sender.start_send(array_buffer.clone());
+                _ = raw_sender.start_send(array_buffer);
             }
         });
     stream_microphone(
@@ -304,9 +654,20 @@ async fn start_recording(from_display: bool) -> Option<impl AsyncSource + Unpin>
 
     let first = receiver.next().await?;
     let sample_rate = first.sample_rate;
-    Some(AsyncSourceFromStream::new(
-        receiver.flat_map(|content| stream::iter(content.samples)),
-        sample_rate,
+    Some((
+        Denoise::new(
+            MeteredGain::new(
+                AsyncSourceFromStream::new(
+                    receiver.flat_map(|content| stream::iter(content.samples)),
+                    sample_rate,
+                ),
+                gain,
+                on_levels,
+            ),
+            denoise_enabled,
+            denoiser_speech_probability,
+        ),
+        raw_receiver,
     ))
 }
 
@@ -323,17 +684,87 @@ async fn load_model(
 
 async fn start_web_sys_audio_stream(
     from_display: bool,
+    denoise_enabled: Rc<Cell<bool>>,
+    gain: Rc<Cell<f32>>,
+    on_levels: impl FnMut(Levels) + 'static,
     mut chunks: Store<Vec<EditableSegment>>,
+    mut live_transcript: Signal<PartialTranscript>,
+    stabilization_level: Rc<Cell<StabilizationLevel>>,
     model: Whisper,
 ) -> dioxus::Result<()> {
-    let Some(audio) = start_recording(from_display).await else {
+    let denoiser_speech_probability = Rc::new(Cell::new(0.0));
+    let Some((audio, raw_samples)) = start_recording(
+        from_display,
+        denoise_enabled.clone(),
+        denoiser_speech_probability.clone(),
+        gain,
+        on_levels,
+    )
+    .await
+    else {
         return Ok(());
     };
 
-    let mut stream = audio.transcribe(model);
-    while let Some(text) = stream.next().await {
-        chunks.push(text.into());
-    }
+    let stabilizer = Rc::new(RefCell::new(Stabilizer::new(stabilization_level)));
+    // Set alongside `Stabilizer::reset()` so the redecode task drops its sample history
+    // in lockstep with a finalized utterance, instead of re-decoding (and re-surfacing)
+    // audio that was just committed to `chunks`.
+    let clear_redecode_buffer = Rc::new(Cell::new(false));
+
+    let finalize = {
+        let stabilizer = stabilizer.clone();
+        let clear_redecode_buffer = clear_redecode_buffer.clone();
+        let model = model.clone();
+        async move {
+            let mut stream = audio.transcribe(model);
+            while let Some(segment) = stream.next().await {
+                let mut chunk: EditableSegment = segment.into();
+                if denoise_enabled.get() {
+                    chunk.denoiser_speech_probability = Some(denoiser_speech_probability.get());
+                }
+                chunks.push(chunk);
+                stabilizer.borrow_mut().reset();
+                clear_redecode_buffer.set(true);
+                live_transcript.set(PartialTranscript::default());
+            }
+        }
+    };
+
+    let stabilize = redecode_sliding_window(raw_samples, model, clear_redecode_buffer, move |text| {
+        let partial = stabilizer.borrow_mut().update(&text);
+        live_transcript.set(partial);
+    });
+
+    futures::future::join(finalize, stabilize).await;
+
+    Ok(())
+}
+
+async fn start_file_audio_stream(
+    file: File,
+    mut chunks: Store<Vec<EditableSegment>>,
+    mut decode_progress: Signal<f64>,
+    model: Whisper,
+) -> dioxus::Result<()> {
+    let name = file.name();
+    let Some((audio, mut progress)) = start_file_transcription(file).await else {
+        return Err(format!("couldn't decode \"{name}\" - only WAV files are supported").into());
+    };
+
+    let track_progress = async move {
+        while let Some(update) = progress.next().await {
+            decode_progress.set(update.fraction());
+        }
+    };
+
+    let transcribe = async move {
+        let mut stream = audio.transcribe(model);
+        while let Some(segment) = stream.next().await {
+            chunks.push(segment.into());
+        }
+    };
+
+    futures::future::join(track_progress, transcribe).await;
 
     Ok(())
 }
@@ -0,0 +1,68 @@
+//! Text-to-speech read-back via the browser's Web Speech `SpeechSynthesis` API.
+//!
+//! Mirrors `mic.rs`'s JS-interop pattern: a typed options struct serialized across the
+//! wasm boundary and a small JS shim (`speak.js`) that wraps the callback-based native
+//! API in a promise so Rust can simply `.await` an utterance.
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+/// Options for a single utterance, passed straight through to `SpeechSynthesisUtterance`.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpeakOptions {
+    rate: Option<f32>,
+    pitch: Option<f32>,
+    volume: Option<f32>,
+    voice: Option<String>,
+}
+
+impl SpeakOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_rate(mut self, rate: f32) -> Self {
+        self.rate = Some(rate);
+        self
+    }
+
+    pub fn with_pitch(mut self, pitch: f32) -> Self {
+        self.pitch = Some(pitch);
+        self
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = Some(volume);
+        self
+    }
+
+    pub fn with_voice(mut self, voice: impl Into<String>) -> Self {
+        self.voice = Some(voice.into());
+        self
+    }
+}
+
+/// Import the JavaScript `speak`/`cancelSpeech` functions.
+#[wasm_bindgen(module = "/src/speak.js")]
+extern "C" {
+    #[wasm_bindgen(js_name = speak, catch)]
+    fn speak_js(text: &str, options: JsValue) -> Result<js_sys::Promise, JsValue>;
+
+    #[wasm_bindgen(js_name = cancelSpeech)]
+    fn cancel_speech_js();
+}
+
+/// Speak `text` aloud, resolving once the browser has finished the utterance.
+pub async fn speak(text: &str, options: Option<SpeakOptions>) -> Result<(), JsValue> {
+    let opts_js = serde_wasm_bindgen::to_value(&options.unwrap_or_default())?;
+    let promise = speak_js(text, opts_js)?;
+    JsFuture::from(promise).await?;
+    Ok(())
+}
+
+/// Stop whatever utterance is currently playing.
+pub fn cancel() {
+    cancel_speech_js();
+}
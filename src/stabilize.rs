@@ -0,0 +1,258 @@
+//! Incremental re-decoding and word-level stabilization for live partial transcripts.
+//!
+//! `AsyncSourceTranscribeExt::transcribe` only yields a `Segment` once Whisper's VAD has
+//! finalized it, which leaves the UI a full utterance behind the speaker. This module
+//! re-runs transcription on a sliding window of the most recently buffered samples every
+//! time new audio arrives, and tracks which words have held the same position across
+//! enough consecutive passes to be considered "stable" - similar to AWS Transcribe's
+//! partial-result stabilization.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::time::Duration;
+
+use futures::channel::mpsc::UnboundedReceiver;
+use futures::stream;
+use futures_util::StreamExt;
+use kalosm_sound::{AsyncSourceFromStream, AsyncSourceTranscribeExt, Whisper};
+
+use crate::mic::AudioData;
+
+/// How many consecutive re-decode passes a word must hold its position before it is
+/// committed and stops being re-rendered as part of the unstable tail.
+///
+/// Lower values commit text sooner but churn more as later audio reshapes the decode;
+/// higher values are slower to firm up but rarely revise already-committed words.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StabilizationLevel(pub usize);
+
+impl Default for StabilizationLevel {
+    fn default() -> Self {
+        Self(2)
+    }
+}
+
+/// How much recently buffered audio to re-decode on every new batch.
+const WINDOW: Duration = Duration::from_secs(8);
+
+/// A transcript still being stabilized: the prefix that has been committed and will
+/// never change, plus the unstable tail that may still be rewritten by future passes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PartialTranscript {
+    pub committed: String,
+    pub pending: String,
+}
+
+/// Tracks word-level agreement across re-decodes of a sliding window so that stable
+/// words are committed exactly once while the unstable tail keeps being replaced.
+pub struct Stabilizer {
+    /// Shared with the UI so the stabilization-level slider takes effect on the next
+    /// `update` instead of requiring the capture session to restart.
+    level: Rc<Cell<StabilizationLevel>>,
+    /// Words committed so far. Never revisited once pushed here.
+    committed: Vec<String>,
+    /// The most recent pass's words beyond the committed prefix.
+    previous_tail: Vec<String>,
+    /// For each word in `previous_tail`, how many consecutive passes it has agreed.
+    agreement: Vec<usize>,
+}
+
+impl Stabilizer {
+    pub fn new(level: Rc<Cell<StabilizationLevel>>) -> Self {
+        Self {
+            level,
+            committed: Vec::new(),
+            previous_tail: Vec::new(),
+            agreement: Vec::new(),
+        }
+    }
+
+    /// Start tracking a brand new utterance, discarding any in-progress commit state.
+    pub fn reset(&mut self) {
+        self.committed.clear();
+        self.previous_tail.clear();
+        self.agreement.clear();
+    }
+
+    /// Feed the full re-decoded text of the current window and return the updated
+    /// committed/pending split.
+    ///
+    /// `text` only covers the sliding window (see [`redecode_sliding_window`]), which
+    /// drops audio older than `WINDOW`. Once an utterance runs longer than that, the
+    /// window no longer contains the audio behind words already in `self.committed`,
+    /// so those words won't appear in `text` at all - resync to however much of the
+    /// committed prefix still matches, rather than assuming `text` always starts with
+    /// the full committed prefix at a fixed offset.
+    pub fn update(&mut self, text: &str) -> PartialTranscript {
+        let words: Vec<String> = text.split_whitespace().map(str::to_string).collect();
+
+        // Find the longest suffix of `committed` that matches a prefix of `words`: once
+        // the window has aged out some committed words, `words` no longer starts with
+        // the full committed prefix, but it still overlaps with however much of the
+        // committed *tail* is still within the window.
+        let max_overlap = self.committed.len().min(words.len());
+        let overlap = (0..=max_overlap)
+            .rev()
+            .find(|&k| self.committed[self.committed.len() - k..] == words[..k])
+            .unwrap_or(0);
+        let tail: Vec<String> = words[overlap..].to_vec();
+
+        let agreement: Vec<usize> = tail
+            .iter()
+            .enumerate()
+            .map(|(i, word)| {
+                let agrees = self.previous_tail.get(i) == Some(word);
+                let previous_count = self.agreement.get(i).copied().unwrap_or(0);
+                if agrees { previous_count + 1 } else { 1 }
+            })
+            .collect();
+
+        let level = self.level.get().0;
+        let commit_up_to = agreement
+            .iter()
+            .take_while(|count| **count >= level)
+            .count();
+
+        self.committed.extend(tail[..commit_up_to].iter().cloned());
+        self.previous_tail = tail[commit_up_to..].to_vec();
+        self.agreement = agreement[commit_up_to..].to_vec();
+
+        PartialTranscript {
+            committed: self.committed.join(" "),
+            pending: self.previous_tail.join(" "),
+        }
+    }
+}
+
+/// Re-decode a sliding window of the most recent samples on every new `AudioData` batch,
+/// calling `on_pass` with the full re-decoded text of that window.
+///
+/// Stabilization itself is left to the caller (see [`Stabilizer`]) so that it can be
+/// reset independently, e.g. once the corresponding utterance is finalized elsewhere.
+/// When that happens, the caller must also set `clear_buffer`: otherwise this window
+/// would keep re-decoding audio that was already committed, and the same words would
+/// reappear in the unstable tail right after they were "output exactly once" into the
+/// finalized chunk. `model` is cloned for each pass so the caller can keep transcribing
+/// the finalized stream from the same source concurrently.
+pub async fn redecode_sliding_window(
+    mut raw_samples: UnboundedReceiver<AudioData>,
+    model: Whisper,
+    clear_buffer: Rc<Cell<bool>>,
+    mut on_pass: impl FnMut(String),
+) {
+    let mut buffer: VecDeque<f32> = VecDeque::new();
+
+    while let Some(mut batch) = raw_samples.next().await {
+        // Coalesce any batches that queued up while the previous pass was still
+        // decoding: re-decoding an 8s window takes far longer than the ~100ms between
+        // batches, so draining everything already available keeps this from falling
+        // further and further behind real time.
+        let mut samples = batch.samples;
+        let mut sample_rate = batch.sample_rate;
+        while let Ok(Some(next)) = raw_samples.try_next() {
+            batch = next;
+            samples.extend(batch.samples);
+            sample_rate = batch.sample_rate;
+        }
+
+        if clear_buffer.replace(false) {
+            buffer.clear();
+        }
+        buffer.extend(samples);
+        let max_samples = (WINDOW.as_secs_f32() * sample_rate as f32) as usize;
+        while buffer.len() > max_samples {
+            buffer.pop_front();
+        }
+
+        let window_samples: Vec<f32> = buffer.iter().copied().collect();
+        let source = AsyncSourceFromStream::new(stream::iter(window_samples), sample_rate);
+        let mut pass = source.transcribe(model.clone());
+
+        let mut text = String::new();
+        while let Some(segment) = pass.next().await {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            text.push_str(segment.text());
+        }
+
+        on_pass(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stabilizer(level: usize) -> Stabilizer {
+        Stabilizer::new(Rc::new(Cell::new(StabilizationLevel(level))))
+    }
+
+    #[test]
+    fn commits_once_agreement_threshold_is_reached() {
+        let mut stabilizer = stabilizer(2);
+
+        let first = stabilizer.update("hello world");
+        assert_eq!(first.committed, "");
+        assert_eq!(first.pending, "hello world");
+
+        // Same tail again: each word now has 2 consecutive agreements and commits.
+        let second = stabilizer.update("hello world");
+        assert_eq!(second.committed, "hello world");
+        assert_eq!(second.pending, "");
+    }
+
+    #[test]
+    fn revises_unstable_tail_that_disagrees() {
+        let mut stabilizer = stabilizer(2);
+        stabilizer.update("hello world");
+        // The first word disagreed with the prior pass ("goodbye" instead of "hello"),
+        // so take_while stops there and nothing commits yet even though "world" itself
+        // has now agreed twice.
+        let revised = stabilizer.update("goodbye world");
+        assert_eq!(revised.committed, "");
+        assert_eq!(revised.pending, "goodbye world");
+    }
+
+    #[test]
+    fn resyncs_when_window_no_longer_contains_the_committed_prefix() {
+        // Simulates an utterance longer than the redecode window: once "hello" has been
+        // committed, a later pass's re-decoded window may have aged it out entirely, so
+        // `text` starts partway through what's already committed instead of repeating it.
+        let mut stabilizer = stabilizer(2);
+        stabilizer.update("hello world");
+        let after_commit = stabilizer.update("hello world");
+        assert_eq!(after_commit.committed, "hello world");
+
+        // The sliding window has dropped "hello" - only "world" (already committed) and
+        // the new word "again" are still in range.
+        let first_pass = stabilizer.update("world again");
+        assert_eq!(first_pass.committed, "hello world");
+        assert_eq!(first_pass.pending, "again");
+
+        let second_pass = stabilizer.update("world again");
+        assert_eq!(second_pass.committed, "hello world again");
+        assert_eq!(second_pass.pending, "");
+    }
+
+    #[test]
+    fn empty_text_clears_the_pending_tail() {
+        let mut stabilizer = stabilizer(2);
+        stabilizer.update("hello world");
+        let cleared = stabilizer.update("");
+        assert_eq!(cleared.committed, "");
+        assert_eq!(cleared.pending, "");
+    }
+
+    #[test]
+    fn reset_discards_committed_and_pending_state() {
+        let mut stabilizer = stabilizer(2);
+        stabilizer.update("hello world");
+        stabilizer.update("hello world");
+        stabilizer.reset();
+        let after_reset = stabilizer.update("goodbye");
+        assert_eq!(after_reset.committed, "");
+        assert_eq!(after_reset.pending, "goodbye");
+    }
+}